@@ -0,0 +1,125 @@
+use crate::diagnostic::Diagnostic;
+
+/// An expected diagnostic parsed out of a `//~ ERROR ...` / `//~^ ERROR ...`
+/// comment in the source file, as an alternative to maintaining a separate
+/// `.stderr` snapshot for tests with only a handful of expected errors.
+pub struct Annotation {
+    pub line: usize,
+    pub level: String,
+    pub message: String,
+}
+
+/// Parses every `//~` annotation out of `source`. A bare `//~ LEVEL msg`
+/// targets the line it appears on; each leading `^` in `//~^^ LEVEL msg`
+/// shifts the target up one additional line.
+pub fn parse(source: &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let Some(pos) = line.find("//~") else {
+            continue;
+        };
+
+        let rest = &line[pos + "//~".len()..];
+        let carets = rest.chars().take_while(|&ch| ch == '^').count();
+        let rest = rest[carets..].trim_start();
+
+        let Some((level, message)) = rest.split_once(' ') else {
+            continue;
+        };
+
+        // A malformed `//~^^` with more carets than preceding lines points
+        // nowhere; skip it rather than underflowing the line number.
+        let Some(line) = line_number.checked_sub(carets).filter(|&line| line > 0) else {
+            continue;
+        };
+
+        annotations.push(Annotation {
+            line,
+            level: level.to_ascii_lowercase(),
+            message: message.trim().to_owned(),
+        });
+    }
+
+    annotations
+}
+
+/// Checks that every annotation has a matching diagnostic at the same line
+/// whose message contains the annotation's text, and that every hard error
+/// rustc produced is accounted for by some annotation. Returns a message
+/// describing the first mismatch found, if any.
+pub fn check(annotations: &[Annotation], diagnostics: &[Diagnostic]) -> Result<(), String> {
+    let mut unmatched: Vec<&Diagnostic> = diagnostics.iter().collect();
+
+    for annotation in annotations {
+        let position = unmatched.iter().position(|diagnostic| {
+            diagnostic.level_str() == annotation.level
+                && diagnostic.message_str().contains(&annotation.message)
+                && diagnostic.primary_line() == Some(annotation.line)
+        });
+
+        match position {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => {
+                return Err(format!(
+                    "expected {} on line {} matching {:?} was not emitted",
+                    annotation.level, annotation.line, annotation.message
+                ));
+            }
+        }
+    }
+
+    if let Some(diagnostic) = unmatched.iter().find(|diagnostic| diagnostic.is_error()) {
+        return Err(format!(
+            "unannotated {}: {}",
+            diagnostic.level_str(),
+            diagnostic.message_str()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic;
+
+    #[test]
+    fn parses_a_caret_pointing_at_the_previous_line() {
+        let source = "let mut x = 1;\n//~^ WARNING variable does not need to be mutable\n";
+        let annotations = parse(source);
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].line, 1);
+        assert_eq!(annotations[0].level, "warning");
+        assert_eq!(annotations[0].message, "variable does not need to be mutable");
+    }
+
+    #[test]
+    fn a_caret_past_the_start_of_the_file_is_skipped_not_a_panic() {
+        let source = "//~^^ ERROR nothing\n";
+        assert!(parse(source).is_empty());
+    }
+
+    #[test]
+    fn check_matches_a_diagnostic_at_the_annotated_line() {
+        let json = br#"{"message":"mismatched types","level":"error","code":null,"spans":[{"file_name":"src/main.rs","line_start":1,"column_start":1,"is_primary":true,"byte_start":0,"byte_end":1,"suggested_replacement":null,"suggestion_applicability":null}],"children":[]}"#;
+        let diagnostics = diagnostic::parse(json);
+        let annotations = vec![Annotation {
+            line: 1,
+            level: "error".to_owned(),
+            message: "mismatched types".to_owned(),
+        }];
+        assert!(check(&annotations, &diagnostics).is_ok());
+    }
+
+    #[test]
+    fn check_fails_on_an_unannotated_error() {
+        let json = br#"{"message":"mismatched types","level":"error","code":null,"spans":[{"file_name":"src/main.rs","line_start":1,"column_start":1,"is_primary":true,"byte_start":0,"byte_end":1,"suggested_replacement":null,"suggestion_applicability":null}],"children":[]}"#;
+        let diagnostics = diagnostic::parse(json);
+        assert!(check(&[], &diagnostics).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::normalize::{self, Context};
+use serde::Deserialize;
+use std::fmt::Write as _;
+
+/// A structured rustc diagnostic, parsed from one line of
+/// `--error-format=json` output. This is an opt-in alternative to the
+/// textual line filtering in `normalize::filter`, for projects that would
+/// rather have their snapshots be robust to cosmetic rendering changes
+/// (underline art, note reordering) than to read the raw rustc text.
+#[derive(Deserialize)]
+pub struct Diagnostic {
+    message: String,
+    level: String,
+    code: Option<Code>,
+    #[serde(default)]
+    pub(crate) spans: Vec<Span>,
+    // Most span_suggestions (e.g. "remove this `mut`") are attached to a
+    // help/note child rather than to the top-level diagnostic.
+    #[serde(default)]
+    pub(crate) children: Vec<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Code {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Span {
+    pub(crate) file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+    pub(crate) byte_start: usize,
+    pub(crate) byte_end: usize,
+    pub(crate) suggested_replacement: Option<String>,
+    pub(crate) suggestion_applicability: Option<Applicability>,
+}
+
+/// How confident rustc is that a suggested replacement can be applied
+/// mechanically, as opposed to needing a human to double check it. Only
+/// `MachineApplicable` suggestions are safe to apply for `.fixed` testing.
+#[derive(Deserialize, PartialEq)]
+pub(crate) enum Applicability {
+    MachineApplicable,
+    MaybeIncorrect,
+    HasPlaceholders,
+    Unspecified,
+}
+
+/// Parses a `--error-format=json` stream into the subset of diagnostics
+/// worth comparing: top-level messages with at least one span. Rustc emits
+/// some non-diagnostic lines (artifact notifications, the final summary)
+/// which are silently skipped.
+pub fn parse(output: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for line in output.split(|&byte| byte == b'\n') {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Ok(diagnostic) = serde_json::from_slice::<Diagnostic>(line) {
+            if !diagnostic.spans.is_empty() {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Renders parsed diagnostics to a stable, line-oriented canonical form
+/// suitable for saving as a snapshot: one header line per diagnostic followed
+/// by a `-->` line for each primary span (and, indented, the same for each
+/// child note/help), with paths replaced by `$DIR`/`$WORKSPACE`/
+/// `$RUST` and, optionally, line/column numbers blanked out so unrelated
+/// line-number churn doesn't break the snapshot.
+pub fn normalize(diagnostics: &[Diagnostic], context: Context, blank_positions: bool) -> String {
+    let mut rendered = String::new();
+
+    if let Some(revision) = context.revision {
+        let _ = writeln!(rendered, "[{}]", revision);
+    }
+
+    for diagnostic in diagnostics {
+        render(diagnostic, context, blank_positions, 0, &mut rendered);
+    }
+
+    rendered
+}
+
+fn render(
+    diagnostic: &Diagnostic,
+    context: Context,
+    blank_positions: bool,
+    depth: usize,
+    rendered: &mut String,
+) {
+    let indent = "  ".repeat(depth);
+
+    let _ = write!(rendered, "{}{}", indent, diagnostic.level);
+    if let Some(code) = &diagnostic.code {
+        let _ = write!(rendered, "[{}]", code.code);
+    }
+    let _ = write!(rendered, ": {}", diagnostic.message);
+
+    for span in &diagnostic.spans {
+        if !span.is_primary {
+            continue;
+        }
+        let file = normalize_path(&span.file_name, context);
+        if blank_positions {
+            let _ = write!(rendered, "\n{}  --> {}", indent, file);
+        } else {
+            let _ = write!(
+                rendered,
+                "\n{}  --> {}:{}:{}",
+                indent, file, span.line_start, span.column_start
+            );
+        }
+    }
+
+    rendered.push('\n');
+
+    for child in &diagnostic.children {
+        render(child, context, blank_positions, depth + 1, rendered);
+    }
+}
+
+impl Diagnostic {
+    pub fn level_str(&self) -> &str {
+        &self.level
+    }
+
+    pub fn message_str(&self) -> &str {
+        &self.message
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == "error"
+    }
+
+    /// The 1-indexed line of this diagnostic's primary span, if it has one.
+    pub fn primary_line(&self) -> Option<usize> {
+        self.spans
+            .iter()
+            .find(|span| span.is_primary)
+            .map(|span| span.line_start)
+    }
+
+    /// All spans on this diagnostic and, recursively, on its children —
+    /// where most suggestions (e.g. "remove this `mut`") actually live.
+    pub(crate) fn all_spans(&self) -> Vec<&Span> {
+        let mut spans: Vec<&Span> = self.spans.iter().collect();
+        for child in &self.children {
+            spans.extend(child.all_spans());
+        }
+        spans
+    }
+}
+
+fn normalize_path(file_name: &str, context: Context) -> String {
+    // .../lib/rustlib/src/rust/src/libstd/net/ip.rs -> $RUST/libstd/net/ip.rs
+    if let Some(pos) = file_name.find("/rustlib/src/rust/src/") {
+        let after = &file_name[pos + "/rustlib/src/rust/src/".len()..];
+        return format!("$RUST/{}", after);
+    }
+
+    normalize::replace_paths(file_name.to_owned(), context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn context<'a>(source_dir: &'a Path, workspace: &'a Path) -> Context<'a> {
+        Context {
+            krate: "mycrate",
+            source_dir,
+            workspace,
+            revision: None,
+        }
+    }
+
+    #[test]
+    fn parse_skips_blank_and_malformed_lines() {
+        let input = b"\n{not json}\n{\"message\":\"oops\",\"level\":\"error\",\"code\":null,\"spans\":[{\"file_name\":\"src/main.rs\",\"line_start\":1,\"column_start\":1,\"is_primary\":true,\"byte_start\":0,\"byte_end\":1,\"suggested_replacement\":null,\"suggestion_applicability\":null}],\"children\":[]}\n";
+        let diagnostics = parse(input);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message_str(), "oops");
+    }
+
+    #[test]
+    fn parse_drops_diagnostics_with_no_spans() {
+        let input = br#"{"message":"note only","level":"note","code":null,"spans":[],"children":[]}"#;
+        assert!(parse(input).is_empty());
+    }
+
+    #[test]
+    fn normalize_renders_children_indented() {
+        let json = br#"{"message":"mismatched types","level":"error","code":null,"spans":[{"file_name":"src/main.rs","line_start":2,"column_start":5,"is_primary":true,"byte_start":0,"byte_end":1,"suggested_replacement":null,"suggestion_applicability":null}],"children":[{"message":"try this","level":"help","code":null,"spans":[],"children":[]}]}"#;
+        let diagnostics = parse(json);
+        let source_dir = Path::new("/tmp/src");
+        let workspace = Path::new("/tmp");
+        let rendered = normalize(&diagnostics, context(source_dir, workspace), true);
+        assert!(rendered.contains("error: mismatched types"));
+        assert!(rendered.contains("  help: try this"));
+    }
+
+    #[test]
+    fn normalize_path_replaces_rustlib_with_rust() {
+        let source_dir = Path::new("/tmp/src");
+        let workspace = Path::new("/tmp");
+        let file =
+            "/home/user/.rustup/toolchains/stable/lib/rustlib/src/rust/src/libstd/net/ip.rs";
+        assert_eq!(
+            normalize_path(file, context(source_dir, workspace)),
+            "$RUST/libstd/net/ip.rs",
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use similar::{ChangeTag, TextDiff};
+use std::io::IsTerminal;
+
+/// Renders a line-oriented diff between the saved snapshot and the actual
+/// compiler output, with changed lines marked `-`/`+` and colored when
+/// stderr is a terminal. This is what gets printed when none of the
+/// `Variations` in `normalize::diagnostics` match the saved `.stderr`, so a
+/// single changed path or reordered note is obvious without eyeballing two
+/// full blocks of text.
+pub fn diff(expected: &str, actual: &str) -> String {
+    render(expected, actual, std::io::stderr().is_terminal())
+}
+
+fn render(expected: &str, actual: &str, colored: bool) -> String {
+    let diff = TextDiff::from_lines(expected, actual);
+
+    let mut rendered = String::new();
+    for group in diff.grouped_ops(3) {
+        for op in group {
+            for change in diff.iter_changes(&op) {
+                let (sign, color) = match change.tag() {
+                    ChangeTag::Delete => ('-', RED),
+                    ChangeTag::Insert => ('+', GREEN),
+                    ChangeTag::Equal => (' ', ""),
+                };
+                if colored && !color.is_empty() {
+                    rendered.push_str(color);
+                }
+                rendered.push(sign);
+                rendered.push(' ');
+                let value = change.value();
+                rendered.push_str(value);
+                if colored && !color.is_empty() {
+                    rendered.push_str(RESET);
+                }
+                if !value.ends_with('\n') {
+                    rendered.push('\n');
+                }
+            }
+        }
+    }
+
+    rendered
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_changed_lines_without_inserting_blank_lines() {
+        let expected = "line1\nline2\nline3\n";
+        let actual = "line1\nCHANGED\nline3\n";
+        assert_eq!(render(expected, actual, false), "  line1\n- line2\n+ CHANGED\n  line3\n");
+    }
+
+    #[test]
+    fn identical_text_produces_no_diff() {
+        let text = "line1\nline2\n";
+        assert_eq!(render(text, text, false), "");
+    }
+}
@@ -0,0 +1,146 @@
+use crate::diagnostic::{self, Applicability};
+use crate::normalize::{self, Context};
+
+/// Applies the machine-applicable suggestions found in a `--error-format=json`
+/// diagnostic stream to `source`, the same way `rustfix`/`cargo fix` would.
+/// `file_name` is the path rustc was invoked on, matched against each span's
+/// own `file_name` so a suggestion that lands in a different file (macro or
+/// aux-build expansion) isn't spliced into `source` using the wrong file's
+/// byte offsets. Returns `None` if there were no applicable suggestions to
+/// apply, which means there is nothing meaningful to compare against a
+/// `.fixed` file.
+pub fn apply_suggestions(file_name: &str, source: &str, json: &[u8]) -> Option<String> {
+    let mut edits = Vec::new();
+
+    for diagnostic in diagnostic::parse(json) {
+        for span in diagnostic.all_spans() {
+            if span.file_name != file_name {
+                continue;
+            }
+            if !matches!(
+                span.suggestion_applicability,
+                Some(Applicability::MachineApplicable)
+            ) {
+                continue;
+            }
+            if let Some(replacement) = &span.suggested_replacement {
+                edits.push((span.byte_start, span.byte_end, replacement.clone()));
+            }
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    edits.sort_by_key(|&(byte_start, ..)| byte_start);
+
+    let mut non_overlapping: Vec<(usize, usize, String)> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if let Some(previous) = non_overlapping.last() {
+            if edit.0 < previous.1 {
+                // Overlaps the previous edit; drop it so the splice below
+                // stays deterministic.
+                continue;
+            }
+        }
+        non_overlapping.push(edit);
+    }
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut position = 0;
+    for (byte_start, byte_end, replacement) in non_overlapping {
+        fixed.push_str(&source[position..byte_start]);
+        fixed.push_str(&replacement);
+        position = byte_end;
+    }
+    fixed.push_str(&source[position..]);
+
+    Some(fixed)
+}
+
+/// Applies the same `$CRATE`/`$DIR`/`$WORKSPACE` path substitutions that
+/// `.stderr` normalization uses to a rustfix-applied source file, then trims
+/// trailing whitespace so it can be compared directly against the committed
+/// `.fixed` file. None of the `.stderr` line-stripping steps are applied.
+pub fn normalize(fixed: String, context: Context) -> String {
+    normalize::trim(normalize::replace_paths(fixed, context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn diagnostic_json(
+        file_name: &str,
+        byte_start: usize,
+        byte_end: usize,
+        replacement: &str,
+        applicability: &str,
+    ) -> String {
+        format!(
+            r#"{{"message":"m","level":"error","code":null,"spans":[{{"file_name":"{}","line_start":1,"column_start":1,"is_primary":true,"byte_start":{},"byte_end":{},"suggested_replacement":"{}","suggestion_applicability":"{}"}}],"children":[]}}"#,
+            file_name, byte_start, byte_end, replacement, applicability,
+        )
+    }
+
+    #[test]
+    fn applies_a_machine_applicable_suggestion() {
+        let json = diagnostic_json("src/main.rs", 4, 9, "WORLD", "MachineApplicable");
+        let fixed = apply_suggestions("src/main.rs", "let HELLO = 1;", json.as_bytes());
+        assert_eq!(fixed.as_deref(), Some("let WORLD = 1;"));
+    }
+
+    #[test]
+    fn ignores_suggestions_in_a_different_file() {
+        let json = diagnostic_json("src/other.rs", 4, 9, "WORLD", "MachineApplicable");
+        let fixed = apply_suggestions("src/main.rs", "let HELLO = 1;", json.as_bytes());
+        assert_eq!(fixed, None);
+    }
+
+    #[test]
+    fn ignores_non_machine_applicable_suggestions() {
+        let json = diagnostic_json("src/main.rs", 4, 9, "WORLD", "MaybeIncorrect");
+        let fixed = apply_suggestions("src/main.rs", "let HELLO = 1;", json.as_bytes());
+        assert_eq!(fixed, None);
+    }
+
+    #[test]
+    fn drops_the_later_of_two_overlapping_edits() {
+        let mut json = diagnostic_json("src/main.rs", 0, 5, "AAAAA", "MachineApplicable");
+        json.push('\n');
+        json.push_str(&diagnostic_json(
+            "src/main.rs",
+            3,
+            8,
+            "BBBBB",
+            "MachineApplicable",
+        ));
+        let fixed = apply_suggestions("src/main.rs", "01234567890", json.as_bytes());
+        assert_eq!(fixed.as_deref(), Some("AAAAA567890"));
+    }
+
+    #[test]
+    fn walks_suggestions_attached_to_children() {
+        let child = diagnostic_json("src/main.rs", 4, 9, "WORLD", "MachineApplicable");
+        let json = format!(
+            r#"{{"message":"m","level":"warning","code":null,"spans":[{{"file_name":"src/main.rs","line_start":1,"column_start":1,"is_primary":true,"byte_start":0,"byte_end":0,"suggested_replacement":null,"suggestion_applicability":null}}],"children":[{}]}}"#,
+            child,
+        );
+        let fixed = apply_suggestions("src/main.rs", "let HELLO = 1;", json.as_bytes());
+        assert_eq!(fixed.as_deref(), Some("let WORLD = 1;"));
+    }
+
+    #[test]
+    fn normalize_trims_and_substitutes_paths() {
+        let context = Context {
+            krate: "mycrate",
+            source_dir: Path::new("/tmp/src"),
+            workspace: Path::new("/tmp"),
+            revision: None,
+        };
+        let fixed = "fn main() {}\n\n".to_owned();
+        assert_eq!(normalize(fixed, context), "fn main() {}\n");
+    }
+}
@@ -5,6 +5,7 @@ pub struct Context<'a> {
     pub krate: &'a str,
     pub source_dir: &'a Path,
     pub workspace: &'a Path,
+    pub revision: Option<&'a str>,
 }
 
 pub fn trim<S: AsRef<[u8]>>(output: S) -> String {
@@ -84,6 +85,10 @@ use self::Normalization::*;
 fn apply(original: &str, normalization: Normalization, context: Context) -> String {
     let mut normalized = String::new();
 
+    if let Some(revision) = context.revision {
+        normalized += &format!("[{}]\n", revision);
+    }
+
     for line in original.lines() {
         if let Some(line) = filter(line, normalization, context) {
             normalized += &line;
@@ -164,12 +169,17 @@ fn filter(line: &str, normalization: Normalization, context: Context) -> Option<
         line.truncate(line.trim_end().len());
     }
 
-    line = line
-        .replace(context.krate, "$CRATE")
-        .replace_case_insensitive(context.source_dir, "$DIR")
-        .replace_case_insensitive(context.workspace, "$WORKSPACE");
+    Some(replace_paths(line, context))
+}
 
-    Some(line)
+/// Replaces occurrences of the crate name, source dir, and workspace dir with
+/// their `$CRATE`/`$DIR`/`$WORKSPACE` placeholders. Shared by the stderr line
+/// filter above and by anything else that needs the same substitutions
+/// applied to a whole blob of text, such as `fixed` rustfix output.
+pub(crate) fn replace_paths(line: String, context: Context) -> String {
+    line.replace(context.krate, "$CRATE")
+        .replace_case_insensitive(context.source_dir, "$DIR")
+        .replace_case_insensitive(context.workspace, "$WORKSPACE")
 }
 
 trait ReplaceCaseInsensitive {
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+/// Parses a `// revisions: a b c` directive out of a test's leading comment
+/// header, returning the empty Vec if the file declares none. When a file
+/// has revisions, it is compiled once per revision, each with `--cfg
+/// $revision` passed to rustc.
+pub fn parse(source: &str) -> Vec<String> {
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(line) = line.strip_prefix("//") else {
+            // The header ends at the first non-comment line.
+            break;
+        };
+        if let Some(revisions) = line.trim_start().strip_prefix("revisions:") {
+            return revisions.split_whitespace().map(str::to_owned).collect();
+        }
+    }
+
+    Vec::new()
+}
+
+/// The `--cfg` flag that selects a given revision when invoking rustc.
+pub fn cfg_flag(revision: &str) -> String {
+    format!("--cfg={}", revision)
+}
+
+/// The snapshot path for a revision of `path`, e.g. `test.a.stderr` instead
+/// of `test.stderr`.
+pub fn snapshot_path(path: &Path, revision: Option<&str>, extension: &str) -> PathBuf {
+    match revision {
+        Some(revision) => path.with_extension(format!("{}.{}", revision, extension)),
+        None => path.with_extension(extension),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_revisions_from_the_header() {
+        let source = "// revisions: a b c\nfn main() {}\n";
+        assert_eq!(parse(source), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn returns_empty_when_there_is_no_directive() {
+        let source = "fn main() {}\n";
+        assert!(parse(source).is_empty());
+    }
+
+    #[test]
+    fn stops_scanning_at_the_first_non_comment_line() {
+        let source = "fn main() {\n    // revisions: a b\n}\n";
+        assert!(parse(source).is_empty());
+    }
+
+    #[test]
+    fn snapshot_path_suffixes_the_revision() {
+        let path = Path::new("tests/ui/test.rs");
+        assert_eq!(
+            snapshot_path(path, Some("a"), "stderr"),
+            Path::new("tests/ui/test.a.stderr"),
+        );
+        assert_eq!(
+            snapshot_path(path, None, "stderr"),
+            Path::new("tests/ui/test.stderr"),
+        );
+    }
+}